@@ -1,13 +1,14 @@
-use chrono::{Duration, Local, NaiveDate};
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use plotters::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 pub mod console_backend;
+pub mod watcher;
 
 const DAY_OFFSET: Duration = Duration::hours(2);
 
-fn today() -> NaiveDate {
+pub fn today() -> NaiveDate {
     Local::now()
         .checked_sub_signed(DAY_OFFSET)
         .unwrap()
@@ -26,49 +27,254 @@ fn days_within_last(duration: Duration) -> impl Iterator<Item = NaiveDate> {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Habit<'a> {
+pub struct Habit {
     progress: HashMap<NaiveDate, i32>,
-    name: &'a str,
-    habit_type: HabitType<'a>,
+    name: String,
+    habit_type: HabitType,
+    #[serde(default)]
+    goal: Option<i32>,
+    #[serde(default)]
+    auto: bool,
+    #[serde(default)]
+    watch_path: Option<String>,
+    #[serde(default)]
+    schedule: Schedule,
 }
 
+/// How often a habit is expected to be done. `plotting_data` and
+/// `adherence` only count the days a habit is actually scheduled on.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum HabitType<'a> {
-    Checklist {
-        #[serde(borrow)]
-        objectives: Vec<&'a str>,
-    },
+pub enum Schedule {
+    Daily,
+    DaysOfWeek(Vec<Weekday>),
+    EveryN(u32),
+}
+
+impl Default for Schedule {
+    fn default() -> Schedule {
+        Schedule::Daily
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HabitType {
+    Checklist { objectives: Vec<String> },
     Numerical,
 }
 
-impl<'a> HabitType<'a> {
-    pub fn numerical() -> HabitType<'a> {
+impl HabitType {
+    pub fn numerical() -> HabitType {
         HabitType::Numerical
     }
 
-    pub fn checklist(objectives: Vec<&'a str>) -> HabitType<'a> {
+    pub fn checklist(objectives: Vec<String>) -> HabitType {
         HabitType::Checklist { objectives }
     }
 }
 
 use HabitType as T;
 
-impl<'a> Habit<'a> {
-    pub fn new(name: &'a str, habit_type: HabitType<'a>) -> Habit<'a> {
-        Habit::<'a> {
+impl Habit {
+    pub fn new(name: String, habit_type: HabitType, goal: Option<i32>, schedule: Schedule) -> Habit {
+        Habit {
             progress: HashMap::new(),
             name,
             habit_type,
+            goal,
+            auto: false,
+            watch_path: None,
+            schedule,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn set_goal(&mut self, goal: Option<i32>) {
+        self.goal = goal;
+    }
+
+    /// Changes this habit's type, clearing any recorded progress: a
+    /// checklist's progress is a bitmask of finished objectives while a
+    /// numerical habit's is a running total, so progress recorded under the
+    /// old type would be silently misinterpreted under the new one.
+    pub fn set_habit_type(&mut self, habit_type: HabitType) {
+        self.habit_type = habit_type;
+        self.progress.clear();
+    }
+
+    /// Habit type as used by the `list` query language's `type:` predicate.
+    pub fn type_name(&self) -> &'static str {
+        match &self.habit_type {
+            T::Checklist { .. } => "checklist",
+            T::Numerical => "numerical",
+        }
+    }
+
+    /// Whether any progress has been logged today.
+    pub fn has_progress_today(&self) -> bool {
+        self.progress.get(&today()).map_or(false, |p| *p != 0)
+    }
+
+    /// Whether this habit is due on `date`, according to its schedule.
+    pub fn scheduled_on(&self, date: NaiveDate) -> bool {
+        match &self.schedule {
+            Schedule::Daily => true,
+            Schedule::DaysOfWeek(days) => days.contains(&date.weekday()),
+            Schedule::EveryN(n) => {
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+                (date - epoch).num_days() % (*n).max(1) as i64 == 0
+            }
+        }
+    }
+
+    fn schedule_display(&self) -> String {
+        match &self.schedule {
+            Schedule::Daily => "daily".to_string(),
+            Schedule::DaysOfWeek(days) => {
+                let names = Vec::from_iter(days.iter().map(|day| day.to_string()));
+                names.join(", ")
+            }
+            Schedule::EveryN(n) => format!("every {} days", n),
+        }
+    }
+
+    /// Fraction of scheduled days within `in_last` on which the goal was
+    /// met: completed / days the habit was actually due, not calendar days.
+    pub fn adherence(&self, in_last: Duration) -> f64 {
+        let scheduled_days = Vec::from_iter(days_within_last(in_last).filter(|d| self.scheduled_on(*d)));
+        if scheduled_days.is_empty() {
+            return 1.0;
         }
+        let met = scheduled_days.iter().filter(|d| self.met_goal(d)).count();
+        met as f64 / scheduled_days.len() as f64
     }
 
-    pub fn name(&self) -> &'a str {
-        self.name
+    /// Marks this habit as auto-tracked: progress is logged whenever `path`
+    /// changes (see `watcher::watch_all`), rather than by hand.
+    pub fn set_auto(&mut self, path: String) {
+        self.auto = true;
+        self.watch_path = Some(path);
+    }
+
+    pub fn is_auto(&self) -> bool {
+        self.auto
+    }
+
+    pub fn watch_path(&self) -> Option<&str> {
+        self.watch_path.as_deref()
+    }
+
+    /// Whether `date` meets this habit's goal: for a numerical habit, the
+    /// day's sum is at least `goal` (defaulting to any progress at all if
+    /// no goal was set); for a checklist habit, every objective is done. A
+    /// checklist with no objectives can never be "done", since an empty
+    /// bitmask would otherwise count as trivially complete.
+    fn met_goal(&self, date: &NaiveDate) -> bool {
+        let progress = *self.progress.get(date).unwrap_or(&0);
+        match &self.habit_type {
+            T::Numerical => progress >= self.goal.unwrap_or(1),
+            T::Checklist { objectives } => {
+                if objectives.is_empty() {
+                    return false;
+                }
+                let all_done = (1 << objectives.len() as i32) - 1;
+                progress & all_done == all_done
+            }
+        }
+    }
+
+    /// Number of consecutive scheduled days, walking backwards from today,
+    /// for which the goal was met. Days the habit isn't scheduled on are
+    /// skipped rather than breaking the streak. Stops at the first scheduled
+    /// day whose goal wasn't met, and never walks earlier than the oldest
+    /// recorded progress.
+    pub fn current_streak(&self) -> u32 {
+        let earliest = self.progress.keys().min().copied();
+        let mut streak = 0;
+        let mut date = today();
+        loop {
+            if self.scheduled_on(date) {
+                if !self.met_goal(&date) {
+                    break;
+                }
+                streak += 1;
+            }
+            if earliest.map_or(true, |earliest| date <= earliest) {
+                break;
+            }
+            date = date.pred_opt().unwrap();
+        }
+        streak
+    }
+
+    /// Longest run of consecutive scheduled days with the goal met, found
+    /// anywhere in the recorded progress. Days the habit isn't scheduled on
+    /// are skipped rather than resetting the run.
+    pub fn longest_streak(&self) -> u32 {
+        let mut dates = Vec::from_iter(self.progress.keys());
+        dates.sort();
+        let start = match dates.first() {
+            Some(date) => **date,
+            None => return 0,
+        };
+
+        let mut longest = 0;
+        let mut current = 0;
+        let mut date = start;
+        loop {
+            if self.scheduled_on(date) {
+                if self.met_goal(&date) {
+                    current += 1;
+                    longest = longest.max(current);
+                } else {
+                    current = 0;
+                }
+            }
+            if date >= today() {
+                break;
+            }
+            date = date.succ_opt().unwrap();
+        }
+        longest
+    }
+
+    /// Today's progress against `goal`, for display: `(achieved, goal)`.
+    /// Checklist habits always report objectives done vs. total, whether
+    /// or not a `goal` was set.
+    fn goal_progress_today(&self) -> Option<(i32, i32)> {
+        let progress = *self.progress.get(&today()).unwrap_or(&0);
+        match &self.habit_type {
+            T::Numerical => self.goal.map(|goal| (progress, goal)),
+            T::Checklist { objectives } => {
+                Some((progress.count_ones() as i32, objectives.len() as i32))
+            }
+        }
     }
 
     pub fn display(&self) -> String {
         let mut result = String::new();
         result += format!("{}: ", &self.name).as_str();
+        result += format!(
+            "\n\tStreak: {} (longest: {})",
+            self.current_streak(),
+            self.longest_streak()
+        )
+        .as_str();
+        if let Some((achieved, goal)) = self.goal_progress_today() {
+            result += format!("\n\t{}/{} met today", achieved, goal).as_str();
+        }
+        result += format!("\n\tSchedule: {}", self.schedule_display()).as_str();
+        result += format!(
+            "\n\tAdherence (last 30 days): {:.0}%",
+            self.adherence(Duration::days(30)) * 100.0
+        )
+        .as_str();
 
         match &self.habit_type {
             T::Checklist { objectives } => {
@@ -106,12 +312,17 @@ impl<'a> Habit<'a> {
         }
     }
 
-    pub fn add_progress(&mut self, progress: i32) {
-        let entry = self.progress.entry(today()).or_insert(0);
+    pub fn add_progress(&mut self, progress: i32, date: NaiveDate) {
+        let entry = self.progress.entry(date).or_insert(0);
         *entry += progress;
     }
 
-    pub fn mark_objective(&mut self, objective: &'a str, finished: bool) -> Result<(), String> {
+    pub fn mark_objective(
+        &mut self,
+        objective: &str,
+        finished: bool,
+        date: NaiveDate,
+    ) -> Result<(), String> {
         match &self.habit_type {
             T::Checklist { objectives } => {
                 let mut i = 0;
@@ -128,7 +339,7 @@ impl<'a> Habit<'a> {
                     i += 1;
                 }
                 let flag_to_set = 1 << i as i32;
-                if !((*self.progress.entry(today()).or_default() & flag_to_set != 0) ^ (finished)) {
+                if !((*self.progress.entry(date).or_default() & flag_to_set != 0) ^ (finished)) {
                     return Err(format!(
                         "Objective '{}' already marked as {}.",
                         objective,
@@ -136,7 +347,7 @@ impl<'a> Habit<'a> {
                     ));
                 }
 
-                self.add_progress(1 << i as i32 * (if finished { 1 } else { -1 }));
+                self.add_progress(if finished { flag_to_set } else { -flag_to_set }, date);
                 Ok(())
             }
             _ => Err(format!("{} is not a checklist habit.", &self.name)),
@@ -147,7 +358,9 @@ impl<'a> Habit<'a> {
         &self,
         in_last: Duration,
     ) -> Result<Vec<(i32, i32)>, Box<dyn std::error::Error + 'static>> {
-        let days = Vec::from_iter(days_within_last(in_last));
+        let days = Vec::from_iter(days_within_last(in_last).filter(|d| self.scheduled_on(*d)));
+        let today = today();
+        let offsets = Vec::from_iter(days.iter().map(|day| (*day - today).num_days() as i32));
 
         match &self.habit_type {
             T::Checklist { .. } => {
@@ -163,7 +376,7 @@ impl<'a> Habit<'a> {
                         prog = prog >> 1;
                     }
                 }
-                Ok(Vec::from_iter((1 - days.len() as i32..=0).zip(completed)))
+                Ok(Vec::from_iter(offsets.into_iter().zip(completed)))
             }
             T::Numerical => {
                 let mut progress_during_period = Vec::<i32>::with_capacity(days.len());
@@ -171,13 +384,29 @@ impl<'a> Habit<'a> {
                     progress_during_period.push(*self.progress.get(day).unwrap_or(&0));
                 }
                 Ok(Vec::from_iter(
-                    (1 - days.len() as i32..=0).zip(progress_during_period),
+                    offsets.into_iter().zip(progress_during_period),
                 ))
             }
         }
     }
 
     pub fn plot<DB: DrawingBackend>(
+        &self,
+        drawing_area: &DrawingArea<DB, plotters::coord::Shift>,
+        in_last: Duration,
+        mode: PlotMode,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>>
+    where
+        DB::ErrorType: 'static,
+    {
+        match mode {
+            PlotMode::Line => self.plot_series(drawing_area, in_last, false),
+            PlotMode::Cumulative => self.plot_series(drawing_area, in_last, true),
+            PlotMode::Calendar => self.plot_calendar(drawing_area, in_last),
+        }
+    }
+
+    fn plot_series<DB: DrawingBackend>(
         &self,
         drawing_area: &DrawingArea<DB, plotters::coord::Shift>,
         in_last: Duration,
@@ -211,13 +440,14 @@ impl<'a> Habit<'a> {
             (T::Numerical, true) => "Total Progress by day",
         };
 
+        let min_x = data.iter().map(|(x, _)| *x).min().unwrap_or(0);
         let mut chart = ChartBuilder::on(drawing_area)
             .margin(1)
             .set_label_area_size(LabelAreaPosition::Left, (5i32).percent_width())
             .set_label_area_size(LabelAreaPosition::Bottom, (10i32).percent_height())
             .caption(title, font)
             .build_cartesian_2d(
-                (1 - data.len() as i32)..0,
+                min_x..0,
                 0..*data.iter().map(|(_, y)| y).max().unwrap_or(&1),
             )?;
 
@@ -229,4 +459,134 @@ impl<'a> Habit<'a> {
         )?;
         Ok(())
     }
+
+    /// Completion fraction on `date`, used to shade a calendar cell: value
+    /// relative to `max` for numerical habits, objectives done for
+    /// checklists.
+    fn intensity_on(&self, date: &NaiveDate, max: i32) -> f64 {
+        let progress = *self.progress.get(date).unwrap_or(&0);
+        match &self.habit_type {
+            T::Numerical => {
+                if max <= 0 {
+                    0.0
+                } else {
+                    (progress as f64 / max as f64).clamp(0.0, 1.0)
+                }
+            }
+            T::Checklist { objectives } => {
+                if objectives.is_empty() {
+                    0.0
+                } else {
+                    progress.count_ones() as f64 / objectives.len() as f64
+                }
+            }
+        }
+    }
+
+    /// A GitHub-style month grid: weeks as columns, weekdays as rows, each
+    /// cell shaded by `intensity_on` between a light and dark shade of the
+    /// habit's usual plot color.
+    fn plot_calendar<DB: DrawingBackend>(
+        &self,
+        drawing_area: &DrawingArea<DB, plotters::coord::Shift>,
+        in_last: Duration,
+    ) -> Result<(), Box<dyn std::error::Error + 'static>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let font = ("sans-serif", (10).percent_height());
+        let days = Vec::from_iter(days_within_last(in_last));
+
+        let max = *self.progress.values().max().unwrap_or(&1);
+
+        let mut mondays = Vec::from_iter(
+            days.iter()
+                .map(|day| *day - Duration::days(day.weekday().num_days_from_monday() as i64)),
+        );
+        mondays.sort();
+        mondays.dedup();
+
+        let mut chart = ChartBuilder::on(drawing_area)
+            .margin(1)
+            .set_label_area_size(LabelAreaPosition::Left, (5i32).percent_width())
+            .caption("Calendar", font)
+            .build_cartesian_2d(0..mondays.len() as i32, 0..7)?;
+
+        chart.configure_mesh().disable_mesh().draw()?;
+
+        chart.draw_series(days.iter().map(|day| {
+            let monday =
+                *day - Duration::days(day.weekday().num_days_from_monday() as i64);
+            let week = mondays.iter().position(|m| *m == monday).unwrap() as i32;
+            let weekday = day.weekday().num_days_from_monday() as i32;
+            Rectangle::new(
+                [(week, weekday), (week + 1, weekday + 1)],
+                calendar_color(self.intensity_on(day, max)).filled(),
+            )
+        }))?;
+
+        Ok(())
+    }
+}
+
+/// Interpolates between a light and dark shade of the habit tracker's usual
+/// plot color, `RGBColor(50, 100, 50)`, by `intensity` (0.0 to 1.0).
+fn calendar_color(intensity: f64) -> RGBColor {
+    let intensity = intensity.clamp(0.0, 1.0);
+    let light = (220, 235, 220);
+    let dark = (50, 100, 50);
+    let lerp = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * intensity) as u8;
+    RGBColor(
+        lerp(light.0, dark.0),
+        lerp(light.1, dark.1),
+        lerp(light.2, dark.2),
+    )
+}
+
+/// Which view `Habit::plot` renders: a per-day series, its running total,
+/// or a month-grid heatmap.
+#[derive(Debug, Clone, Copy)]
+pub enum PlotMode {
+    Line,
+    Cumulative,
+    Calendar,
+}
+
+/// A single predicate in the `list` command's query language, e.g.
+/// `type:checklist`, `streak>3`, `done:today`, or a bare substring to
+/// match against the habit's name. A `list` query is a `Vec<HabitFilter>`,
+/// matched with AND semantics.
+pub enum HabitFilter {
+    Type(String),
+    StreakGreaterThan(u32),
+    DoneToday,
+    NameContains(String),
+}
+
+impl HabitFilter {
+    pub fn parse(text: &str) -> Result<HabitFilter, String> {
+        if let Some(kind) = text.strip_prefix("type:") {
+            Ok(HabitFilter::Type(kind.to_string()))
+        } else if let Some(n) = text.strip_prefix("streak>") {
+            Ok(HabitFilter::StreakGreaterThan(
+                n.parse()
+                    .map_err(|_| format!("'{}' is not a number.", n))?,
+            ))
+        } else if text == "done:today" {
+            Ok(HabitFilter::DoneToday)
+        } else if let Some(name) = text.strip_prefix("name:") {
+            Ok(HabitFilter::NameContains(name.to_string()))
+        } else {
+            Ok(HabitFilter::NameContains(text.to_string()))
+        }
+    }
+
+    pub fn matches(&self, habit: &Habit) -> bool {
+        match self {
+            HabitFilter::Type(kind) => habit.type_name() == kind,
+            HabitFilter::StreakGreaterThan(n) => habit.current_streak() > *n,
+            HabitFilter::DoneToday => habit.has_progress_today(),
+            HabitFilter::NameContains(text) => habit.name().contains(text.as_str()),
+        }
+    }
 }
@@ -1,5 +1,5 @@
-use chrono::Duration;
-use habit_tracker::{Habit, HabitType};
+use chrono::{Duration, NaiveDate, Weekday};
+use habit_tracker::{today, Habit, HabitFilter, HabitType, PlotMode, Schedule};
 use plotters::prelude::*;
 use std::env::args;
 use std::fs;
@@ -9,6 +9,82 @@ fn io_error(text: &str) -> Result<(), Box<dyn std::error::Error + 'static>> {
     Err(Box::new(Error::new(ErrorKind::Other, text)))
 }
 
+/// Parses a trailing date argument such as "yesterday", "3 days ago", or
+/// "2024-01-05", defaulting to today when absent.
+fn parse_date(args: &[String]) -> Result<NaiveDate, Box<dyn std::error::Error + 'static>> {
+    if args.is_empty() {
+        return Ok(today());
+    }
+    Ok(fuzzydate::parse(args.join(" "))?.date())
+}
+
+/// Like `parse_date`, but non-fatal: returns `None` instead of erroring when
+/// `args` is empty or doesn't parse as a date. Used to tell apart "add habit
+/// [progress] [date]" invocations where the leading token could be read as
+/// either an explicit progress value or the start of a date phrase, e.g.
+/// "add habit 3 days ago" (a date) vs. "add habit 3" (a progress value).
+fn try_parse_date(args: &[String]) -> Option<NaiveDate> {
+    if args.is_empty() {
+        return None;
+    }
+    fuzzydate::parse(args.join(" ")).ok().map(|dt| dt.date())
+}
+
+/// Parses the trailing `[progress] [date]` arguments to `a(dd)`. A lone
+/// token is always read as an explicit progress value if it parses as one
+/// (so `add habit 5` is progress=5, today, regardless of whether a date
+/// parser would also accept a bare number as a day-of-month); multi-token
+/// phrases are tried as a date first, so "3 days ago" resolves to a date
+/// instead of having its leading digit read as progress.
+fn parse_progress_and_date(
+    args: &[String],
+) -> Result<(i32, NaiveDate), Box<dyn std::error::Error + 'static>> {
+    match args {
+        [] => Ok((1, today())),
+        [single] => match i32::from_str_radix(single, 10) {
+            Ok(progress) => Ok((progress, today())),
+            Err(_) => Ok((1, parse_date(args)?)),
+        },
+        _ => match try_parse_date(args) {
+            Some(date) => Ok((1, date)),
+            None => {
+                let progress = i32::from_str_radix(&args[0], 10)?;
+                Ok((progress, parse_date(&args[1..])?))
+            }
+        },
+    }
+}
+
+/// Parses an explicit numerical goal, rejecting non-positive values: a
+/// goal of 0 or less would make every day automatically "met", even days
+/// with no progress logged at all.
+fn parse_goal(text: &str) -> Result<i32, Box<dyn std::error::Error + 'static>> {
+    let goal = i32::from_str_radix(text, 10)?;
+    if goal <= 0 {
+        return Err(format!("Goal must be a positive integer, got {}.", goal).into());
+    }
+    Ok(goal)
+}
+
+/// Parses a `sched:` token passed to `create`, e.g. `sched:daily`,
+/// `sched:every:3`, or `sched:mon,wed,fri`.
+fn parse_schedule(text: &str) -> Result<Schedule, Box<dyn std::error::Error + 'static>> {
+    if text == "daily" {
+        Ok(Schedule::Daily)
+    } else if let Some(n) = text.strip_prefix("every:") {
+        Ok(Schedule::EveryN(n.parse()?))
+    } else {
+        let days = text
+            .split(',')
+            .map(|day| {
+                day.parse::<Weekday>()
+                    .map_err(|_| format!("'{}' is not a day of the week.", day))
+            })
+            .collect::<Result<Vec<Weekday>, String>>()?;
+        Ok(Schedule::DaysOfWeek(days))
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
     let database_file = "data.json";
     let file_data = match fs::read_to_string(database_file) {
@@ -31,19 +107,39 @@ fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
             help();
         }
         "l" | "list" => {
-            list(&habits);
+            let filters = args[2..]
+                .iter()
+                .map(|arg| HabitFilter::parse(arg))
+                .collect::<Result<Vec<_>, _>>()?;
+            list(&habits, &filters);
         }
         "c" | "create" => {
             if args.len() < 3 {
                 return io_error("Please enter the name of the habit you want to create.");
             }
-            let name = &args[2];
-            let habit_type = if args.len() > 3 {
-                match args[3].as_str() {
-                    "n" | "numerical" => HabitType::numerical(),
-                    "c" | "checklist" => HabitType::checklist(Vec::from_iter(
-                        args.iter().skip(4).map(|s| s.as_str()),
-                    )),
+            let name = args[2].clone();
+
+            let mut rest = Vec::from_iter(args.iter().skip(3));
+            let schedule = match rest.iter().position(|a| a.starts_with("sched:")) {
+                Some(pos) => parse_schedule(&rest.remove(pos)["sched:".len()..])?,
+                None => Schedule::Daily,
+            };
+
+            let (habit_type, goal) = if !rest.is_empty() {
+                match rest[0].as_str() {
+                    "n" | "numerical" => (
+                        HabitType::numerical(),
+                        match rest.get(1) {
+                            None => None,
+                            Some(goal) => Some(parse_goal(goal)?),
+                        },
+                    ),
+                    "c" | "checklist" => (
+                        HabitType::checklist(Vec::from_iter(
+                            rest.iter().skip(1).map(|s| s.to_string()),
+                        )),
+                        None,
+                    ),
                     kind => {
                         return io_error(
 			    format!("'{}' is not a type of habit. Please enter n(umerical) or c(hecklist)", kind).as_str()
@@ -51,21 +147,17 @@ fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
                     }
                 }
             } else {
-                HabitType::numerical()
+                (HabitType::numerical(), None)
             };
-            create(&mut habits, &name, habit_type);
+            create(&mut habits, name, habit_type, goal, schedule);
         }
         "a" | "add" => {
             if args.len() < 3 {
                 return io_error("Please enter the name of the habit you want to add to.");
             }
             let name = &args[2];
-            let progress = if args.len() > 3 {
-                i32::from_str_radix(&args[3], 10)?
-            } else {
-                1_i32
-            };
-            add(&mut habits, &name, progress)?;
+            let (progress, date) = parse_progress_and_date(&args[3..])?;
+            add(&mut habits, name, progress, date)?;
         }
         command @ ("f" | "finish" | "unf" | "unfinish") => {
             let (finishing, command) = match command {
@@ -87,7 +179,8 @@ fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
             }
             let name = &args[2];
             let objective = &args[3];
-            mark_objective(&mut habits, &name, &objective, finishing)?;
+            let date = parse_date(&args[4..])?;
+            mark_objective(&mut habits, name, objective, finishing, date)?;
         }
         "p" | "plot" => {
             if args.len() < 3 {
@@ -99,7 +192,52 @@ fn main() -> Result<(), Box<dyn std::error::Error + 'static>> {
             } else {
                 Duration::days(i64::from_str_radix(&args[3], 10)?)
             };
-            plot(&habits, &name, duration)?;
+            let mode = match args.get(4).map(|s| s.as_str()) {
+                None | Some("line") => PlotMode::Line,
+                Some("cumulative") => PlotMode::Cumulative,
+                Some("calendar") => PlotMode::Calendar,
+                Some(mode) => {
+                    return io_error(
+                        format!(
+                            "'{}' is not a plot mode. Please enter line, cumulative, or calendar.",
+                            mode
+                        )
+                        .as_str(),
+                    );
+                }
+            };
+            plot(&habits, name, duration, mode)?;
+        }
+        "auto" => {
+            if args.len() < 4 {
+                return io_error("Please enter the name of the habit and the path to watch.");
+            }
+            let name = &args[2];
+            let path = &args[3];
+            set_auto(&mut habits, name, path.clone())?;
+        }
+        "w" | "watch" => {
+            watch(&mut habits, database_file)?;
+        }
+        "rename" => {
+            if args.len() < 4 {
+                return io_error("Please enter the habit's current name and its new name.");
+            }
+            rename(&mut habits, &args[2], args[3].clone())?;
+        }
+        "edit" => {
+            if args.len() < 4 {
+                return io_error(
+                    "Please enter the name of the habit and at least one --goal/--type to change.",
+                );
+            }
+            edit(&mut habits, &args[2], &args[3..])?;
+        }
+        "delete" => {
+            if args.len() < 3 {
+                return io_error("Please enter the name of the habit you want to delete.");
+            }
+            delete(&mut habits, &args[2]);
         }
         command => {
             help();
@@ -120,71 +258,105 @@ habit_tracker:
     h(elp):
 Print this message.
 
-    l(ist):
-List all habits in the database.
+    l(ist) [filter]...:
+List habits in the database matching all of the given filters (default: all habits). Filters: type:checklist|numerical, streak>N, done:today, name:substring.
+
+    c(reate) name [type] [goal | objective 1]... [sched:schedule]:
+Creates a habit. Type can be c(hecklist) or n(umerical). If numerical, an optional integer goal may follow. If a checklist habit, the objectives must be specified. Default numerical. An optional sched: token (e.g. sched:daily, sched:mon,wed,fri, sched:every:3) sets how often the habit recurs. Default daily.
+
+    a(dd) name [progress] [date]:
+Adds progress to a habit. Progress defaults to 1. Date can be natural language like \"yesterday\" or \"3 days ago\" and defaults to today.
+
+    f(inish) name objective [date]:
+Finishes an objective of a checklist habit on the given date, defaulting to today.
 
-    c(reate) name [type] [objective 1]...:
-Creates a habit. Type can be c(hecklist) or n(umerical). If a checklist habit, the objectives must be specified. Default numerical.
+    unf(inish) name objective [date]:
+Unfinishes an objective of a checklist habit on the given date, defaulting to today.
 
-    a(dd) name [progress]:
-Adds progress to a habit. Progress defaults to 1.
+    p(lot) name [days] [mode]:
+Plots the progress of the habit over the past [days] days. Days defaults to 7. Mode can be line, cumulative, or calendar; defaults to line. Saves the graph at graphs/[name].png
 
-    f(inish) name objective:
-Finishes an objective of a checklist habit.
+    auto name path:
+Marks a habit as auto-tracked, logging 1 unit of progress whenever the file at path changes. Auto-tracked habits are skipped by a(dd).
 
-    unf(inish) name objective:
-Unfinishes an objective of a checklist habit.
+    w(atch):
+Watches the paths of all auto-tracked habits and logs progress as their files change. Runs until interrupted.
 
-    p(lot) name [days]:
-Plots the progress of the habit over the past [days] days. Days defaults to 7. Saves the graph at graphs/[name].png
+    rename old new:
+Renames a habit.
+
+    edit name [--goal N] [--type n|c objective 1]...:
+Changes a habit's goal and/or type (and objectives, for a checklist). Changing the type clears all recorded progress, since checklist and numerical habits store progress differently.
+
+    delete name:
+Deletes a habit.
 "
     )
 }
 
-fn list(habits: &Vec<Habit>) {
-    for habit in habits {
+fn list(habits: &Vec<Habit>, filters: &[HabitFilter]) {
+    for habit in habits
+        .iter()
+        .filter(|h| filters.iter().all(|f| f.matches(h)))
+    {
         println!("{}", habit.display());
     }
 }
 
-fn create<'a>(habits: &mut Vec<Habit<'a>>, name: &'a str, habit_type: HabitType<'a>) {
-    habits.push(Habit::new(name, habit_type));
+fn create(
+    habits: &mut Vec<Habit>,
+    name: String,
+    habit_type: HabitType,
+    goal: Option<i32>,
+    schedule: Schedule,
+) {
+    habits.push(Habit::new(name, habit_type, goal, schedule));
 }
 
-fn add<'a>(
-    habits: &mut Vec<Habit<'a>>,
-    name: &'a str,
+fn add(
+    habits: &mut Vec<Habit>,
+    name: &str,
     progress: i32,
+    date: NaiveDate,
 ) -> Result<(), Box<dyn std::error::Error + 'static>> {
     let mut iter = habits.iter().enumerate().filter(|(_, h)| h.name() == name);
     match iter.next() {
         None => io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
+        Some((i, _)) if habits[i].is_auto() => io_error(
+            format!(
+                "{} is auto-tracked; run 'watch' instead of adding to it by hand.",
+                name
+            )
+            .as_str(),
+        ),
         Some((i, _)) => {
-            habits[i].add_progress(progress);
+            habits[i].add_progress(progress, date);
             Ok(())
         }
     }
 }
 
-fn mark_objective<'a>(
-    habits: &mut Vec<Habit<'a>>,
-    name: &'a str,
-    objective: &'a str,
+fn mark_objective(
+    habits: &mut Vec<Habit>,
+    name: &str,
+    objective: &str,
     finished: bool,
+    date: NaiveDate,
 ) -> Result<(), Box<dyn std::error::Error + 'static>> {
     match habits.iter().position(|h| h.name() == name) {
         None => io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
-        Some(i) => match habits[i].mark_objective(&objective, finished) {
+        Some(i) => match habits[i].mark_objective(objective, finished, date) {
             Err(e) => io_error(&e),
             _ => Ok(()),
         },
     }
 }
 
-fn plot<'a>(
-    habits: &Vec<Habit<'a>>,
-    name: &'a str,
+fn plot(
+    habits: &Vec<Habit>,
+    name: &str,
     duration: Duration,
+    mode: PlotMode,
 ) -> Result<(), Box<dyn std::error::Error + 'static>> {
     match habits.iter().position(|h| h.name() == name) {
         None => io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
@@ -196,7 +368,7 @@ fn plot<'a>(
 
             let root = root.margin(10, 10, 10, 10);
 
-            habits[i].plot(&root, duration)?;
+            habits[i].plot(&root, duration, mode)?;
 
             root.present()?;
 
@@ -204,3 +376,141 @@ fn plot<'a>(
         }
     }
 }
+
+fn set_auto(
+    habits: &mut Vec<Habit>,
+    name: &str,
+    path: String,
+) -> Result<(), Box<dyn std::error::Error + 'static>> {
+    match habits.iter().position(|h| h.name() == name) {
+        None => io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
+        Some(i) => {
+            habits[i].set_auto(path);
+            Ok(())
+        }
+    }
+}
+
+fn rename(
+    habits: &mut Vec<Habit>,
+    name: &str,
+    new_name: String,
+) -> Result<(), Box<dyn std::error::Error + 'static>> {
+    match habits.iter().position(|h| h.name() == name) {
+        None => io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
+        Some(i) => {
+            if new_name != name && habits.iter().any(|h| h.name() == new_name) {
+                return io_error(
+                    format!(
+                        "Habit {} already exists. Please choose another name.",
+                        new_name
+                    )
+                    .as_str(),
+                );
+            }
+            habits[i].rename(new_name);
+            Ok(())
+        }
+    }
+}
+
+fn edit(
+    habits: &mut Vec<Habit>,
+    name: &str,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let i = match habits.iter().position(|h| h.name() == name) {
+        None => return io_error(format!("Habit {} doesn't seem to exist.", name).as_str()),
+        Some(i) => i,
+    };
+
+    let mut j = 0;
+    while j < args.len() {
+        match args[j].as_str() {
+            "--goal" => {
+                j += 1;
+                let goal = args.get(j).ok_or("--goal requires an integer argument.")?;
+                habits[i].set_goal(Some(parse_goal(goal)?));
+            }
+            "--type" => {
+                j += 1;
+                match args.get(j).map(|s| s.as_str()) {
+                    Some("n") | Some("numerical") => {
+                        habits[i].set_habit_type(HabitType::numerical())
+                    }
+                    Some("c") | Some("checklist") => {
+                        let objectives = Vec::from_iter(args[j + 1..].iter().cloned());
+                        habits[i].set_habit_type(HabitType::checklist(objectives));
+                        j = args.len();
+                    }
+                    kind => {
+                        return io_error(
+                            format!(
+                                "'{}' is not a type of habit. Please enter n(umerical) or c(hecklist)",
+                                kind.unwrap_or("")
+                            )
+                            .as_str(),
+                        );
+                    }
+                }
+            }
+            flag => {
+                return io_error(
+                    format!("'{}' is not an edit flag. Use --goal or --type.", flag).as_str(),
+                );
+            }
+        }
+        j += 1;
+    }
+
+    Ok(())
+}
+
+fn delete(habits: &mut Vec<Habit>, name: &str) {
+    habits.retain(|h| h.name() != name);
+}
+
+fn watch(
+    habits: &mut Vec<Habit>,
+    database_file: &str,
+) -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let paths = habits
+        .iter()
+        .enumerate()
+        .filter_map(|(i, h)| h.watch_path().map(|path| (i, path.to_string())))
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return io_error("No auto-tracked habits to watch.");
+    }
+
+    let (watchers, events) = habit_tracker::watcher::watch_all(paths)?;
+    println!(
+        "Watching {} auto-tracked habit(s). Press Ctrl+C to stop.",
+        watchers.len()
+    );
+
+    for event in events {
+        habits[event.habit_index].add_progress(1, today());
+        fs::write(database_file, serde_json::to_string(&habits)?)?;
+        println!("Logged progress for {}.", habits[event.habit_index].name());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "add habit 5" (no trailing date) must read as an explicit progress
+    /// of 5 logged today, not as fuzzydate treating the bare "5" as a date
+    /// (e.g. a day-of-month) and silently dropping the progress value.
+    #[test]
+    fn parse_progress_and_date_reads_bare_integer_as_progress() {
+        let args = vec!["5".to_string()];
+        let (progress, date) = parse_progress_and_date(&args).unwrap();
+        assert_eq!(progress, 5);
+        assert_eq!(date, today());
+    }
+}
@@ -0,0 +1,38 @@
+//! Auto-tracking for habits that log their own progress by watching an
+//! external file for changes, rather than being updated by hand.
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+
+/// A file-change notification for one watched habit, identified by its
+/// index into the habit list that was passed to `watch_all`.
+pub struct AutoTrackEvent {
+    pub habit_index: usize,
+}
+
+/// Sets up a `notify::RecommendedWatcher` for each `(habit_index, path)`
+/// pair and returns the watchers along with a receiver that yields an
+/// `AutoTrackEvent` every time one of the paths is modified. The watchers
+/// must be kept alive for as long as events should keep arriving.
+pub fn watch_all(
+    paths: Vec<(usize, String)>,
+) -> notify::Result<(Vec<RecommendedWatcher>, Receiver<AutoTrackEvent>)> {
+    let (tx, rx) = channel();
+    let mut watchers = Vec::with_capacity(paths.len());
+
+    for (habit_index, path) in paths {
+        let tx = tx.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                if event.kind.is_modify() {
+                    let _ = tx.send(AutoTrackEvent { habit_index });
+                }
+            }
+        })?;
+        watcher.watch(Path::new(&path), RecursiveMode::NonRecursive)?;
+        watchers.push(watcher);
+    }
+
+    Ok((watchers, rx))
+}